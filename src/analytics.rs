@@ -0,0 +1,126 @@
+use crate::dbctx::HistoryRow;
+
+/// Pass rate, flakiness and duration stats for a single branch, computed
+/// over the last N builds recorded in the history DB.
+#[derive(Debug)]
+pub(crate) struct BranchAnalytics {
+    pub(crate) branch: String,
+    pub(crate) runs: usize,
+    pub(crate) pass_rate: f64,
+    pub(crate) flakiness: f64,
+    pub(crate) avg_duration_secs: Option<f64>,
+}
+
+/// Compute analytics for `branch` from its build history, ordered newest
+/// first (as returned by `DbCtx::history`).
+pub(crate) fn compute(branch: &str, history: &[HistoryRow]) -> BranchAnalytics {
+    let runs = history.len();
+
+    let passes = history
+        .iter()
+        .filter(|row| matches!(row.outcome.as_deref(), Some("success") | Some("fixed")))
+        .count();
+
+    let pass_rate = if runs == 0 {
+        0.0
+    } else {
+        passes as f64 / runs as f64
+    };
+
+    // History is newest-first; walk it oldest-first to count Failed -> Success
+    // transitions between consecutive runs.
+    let chronological = history.iter().rev().collect::<Vec<_>>();
+    let transitions = chronological
+        .windows(2)
+        .filter(|pair| {
+            pair[0].outcome.as_deref() == Some("failed")
+                && matches!(pair[1].outcome.as_deref(), Some("success") | Some("fixed"))
+        })
+        .count();
+
+    let flakiness = if runs == 0 {
+        0.0
+    } else {
+        transitions as f64 / runs as f64
+    };
+
+    let durations = history
+        .iter()
+        .filter_map(|row| match (row.start_time, row.stop_time) {
+            (Some(start), Some(stop)) => Some((stop - start).num_seconds() as f64),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let avg_duration_secs = if durations.is_empty() {
+        None
+    } else {
+        Some(durations.iter().sum::<f64>() / durations.len() as f64)
+    };
+
+    BranchAnalytics {
+        branch: branch.to_string(),
+        runs,
+        pass_rate,
+        flakiness,
+        avg_duration_secs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone, Utc};
+
+    fn row(outcome: &str, start_offset_secs: i64, duration_secs: i64) -> HistoryRow {
+        let start = Utc.timestamp_opt(1_600_000_000 + start_offset_secs, 0).unwrap();
+        HistoryRow {
+            build_num: 0,
+            outcome: Some(outcome.to_string()),
+            start_time: Some(start),
+            stop_time: Some(start + Duration::seconds(duration_secs)),
+        }
+    }
+
+    #[test]
+    fn pass_rate_counts_success_and_fixed() {
+        let history = vec![row("success", 30, 10), row("fixed", 20, 10), row("failed", 10, 10)];
+        let stats = compute("main", &history);
+        assert_eq!(stats.runs, 3);
+        assert!((stats.pass_rate - (2.0 / 3.0)).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn flakiness_counts_failed_to_success_transitions() {
+        // newest first, as DbCtx::history returns it
+        let history = vec![row("success", 30, 10), row("failed", 20, 10), row("success", 10, 10)];
+        let stats = compute("main", &history);
+        assert!((stats.flakiness - (1.0 / 3.0)).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn no_flakiness_without_a_recovery() {
+        let history = vec![row("failed", 20, 10), row("failed", 10, 10)];
+        let stats = compute("main", &history);
+        assert_eq!(stats.flakiness, 0.0);
+    }
+
+    #[test]
+    fn avg_duration_ignores_rows_missing_timestamps() {
+        let mut incomplete = row("success", 10, 10);
+        incomplete.stop_time = None;
+        let history = vec![row("success", 20, 20), incomplete];
+
+        let stats = compute("main", &history);
+        assert_eq!(stats.avg_duration_secs, Some(20.0));
+    }
+
+    #[test]
+    fn empty_history_has_no_duration() {
+        let stats = compute("main", &[]);
+        assert_eq!(stats.runs, 0);
+        assert_eq!(stats.pass_rate, 0.0);
+        assert_eq!(stats.flakiness, 0.0);
+        assert_eq!(stats.avg_duration_secs, None);
+    }
+}
@@ -0,0 +1,92 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+
+use crate::{Build, Result};
+
+/// A single persisted build row, as recorded in `state.db`.
+#[derive(Debug, Clone)]
+pub(crate) struct HistoryRow {
+    pub(crate) build_num: i32,
+    pub(crate) outcome: Option<String>,
+    pub(crate) start_time: Option<DateTime<Utc>>,
+    pub(crate) stop_time: Option<DateTime<Utc>>,
+}
+
+/// Thin wrapper around the local SQLite build history.
+pub(crate) struct DbCtx {
+    conn: Connection,
+}
+
+impl DbCtx {
+    pub(crate) fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS builds (
+                branch     TEXT NOT NULL,
+                build_num  INTEGER NOT NULL,
+                outcome    TEXT,
+                start_time TEXT,
+                stop_time  TEXT,
+                fetched_at TEXT NOT NULL,
+                PRIMARY KEY (branch, build_num)
+            )",
+            [],
+        )?;
+
+        Ok(DbCtx { conn })
+    }
+
+    /// Record every build seen in this poll. A build already stored for
+    /// that (branch, build_num) pair has its outcome/timestamps overwritten
+    /// rather than left alone, since a poll can catch it mid-run (outcome
+    /// "running", no stop_time yet) and a later poll needs to update it to
+    /// its final outcome and duration.
+    pub(crate) fn record_builds(&self, builds: &[Build], fetched_at: DateTime<Utc>) -> Result<()> {
+        for build in builds {
+            self.conn.execute(
+                "INSERT INTO builds (branch, build_num, outcome, start_time, stop_time, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT(branch, build_num) DO UPDATE SET
+                     outcome = excluded.outcome,
+                     start_time = excluded.start_time,
+                     stop_time = excluded.stop_time,
+                     fetched_at = excluded.fetched_at",
+                params![
+                    build.branch,
+                    build.build_num,
+                    build.outcome.as_ref().map(|o| o.kind()),
+                    build.start_time.map(|t| t.to_rfc3339()),
+                    build.stop_time.map(|t| t.to_rfc3339()),
+                    fetched_at.to_rfc3339(),
+                ],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// The most recent `limit` builds recorded for `branch`, newest first.
+    pub(crate) fn history(&self, branch: &str, limit: u32) -> Result<Vec<HistoryRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT build_num, outcome, start_time, stop_time
+             FROM builds
+             WHERE branch = ?1
+             ORDER BY build_num DESC
+             LIMIT ?2",
+        )?;
+
+        let rows = stmt
+            .query_map(params![branch, limit], |row| {
+                Ok(HistoryRow {
+                    build_num: row.get(0)?,
+                    outcome: row.get(1)?,
+                    start_time: row.get(2)?,
+                    stop_time: row.get(3)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(rows)
+    }
+}
@@ -1,10 +1,21 @@
+use chrono::{DateTime, Utc};
 use colored::*;
 use git2::BranchType;
 use git2::Repository;
-use reqwest;
 use serde_derive::Deserialize;
 use structopt::StructOpt;
 
+mod analytics;
+mod dbctx;
+mod notifier;
+mod provider;
+mod serve;
+mod webhook;
+
+use dbctx::DbCtx;
+use notifier::{DesktopNotifier, EmailNotifier, NotifyEvent, Notifier};
+use provider::{make_provider, CiProvider};
+
 #[derive(Debug, StructOpt)]
 struct Cli {
     /// Circleci token
@@ -13,45 +24,237 @@ struct Cli {
     /// variable
     #[structopt(long = "token", short = "t")]
     token: Option<String>,
+
+    /// Print pass rate, flakiness and duration analytics per branch instead
+    /// of (just) the latest build table
+    ///
+    /// Requires build history, which is recorded in `state.db` on every run.
+    #[structopt(long)]
+    analytics: bool,
+
+    /// Number of most recent builds per branch to compute analytics over
+    #[structopt(long, default_value = "20")]
+    analytics_window: u32,
+
+    /// Notify when the current branch's build transitions to/from failing
+    ///
+    /// Requires build history, which is recorded in `state.db` on every run.
+    #[structopt(long)]
+    notify: bool,
+
+    /// Notifier backend to use when --notify is set ("desktop" or "email")
+    #[structopt(long, default_value = "desktop")]
+    notify_backend: String,
+
+    /// CI provider to poll
+    #[structopt(long, env = "CI_PROVIDER", default_value = "circleci")]
+    provider: String,
+
+    #[structopt(subcommand)]
+    cmd: Option<Command>,
 }
 
-type Result<T, E = Box<dyn std::error::Error>> = std::result::Result<T, E>;
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Host a live web dashboard that streams build status updates over SSE
+    Serve {
+        /// Port to listen on
+        #[structopt(long, default_value = "8080")]
+        port: u16,
+
+        /// Seconds between polls of the CircleCI API
+        #[structopt(long, default_value = "15")]
+        interval_secs: u64,
+    },
+    /// Host the same dashboard, updated by a signed webhook instead of polling
+    Webhook {
+        /// Port to listen on
+        #[structopt(long, default_value = "8080")]
+        port: u16,
+
+        /// Shared secret used to verify the X-Hub-Signature-256 header
+        ///
+        /// This argument is optional, if not provided it will look for a WEBHOOK_SECRET
+        /// environment variable
+        #[structopt(long)]
+        secret: Option<String>,
+    },
+}
+
+pub(crate) type Result<T, E = Box<dyn std::error::Error>> = std::result::Result<T, E>;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Cli::from_args();
 
-    let token = if let Some(token) = args.token {
-        token
-    } else {
-        use std::env;
-        env::var("CIRCLECI_TOKEN")
-            .expect("Missing --token argument, or CIRCLECI_TOKEN environment variable")
+    match args.cmd {
+        Some(Command::Serve {
+            port,
+            interval_secs,
+        }) => {
+            let provider = make_provider(&args.provider, resolve_token(args.token))?;
+            serve::serve(provider, serve::ServeOptions { port, interval_secs }).await?;
+        }
+        Some(Command::Webhook { port, secret }) => {
+            let secret = if let Some(secret) = secret {
+                secret
+            } else {
+                std::env::var("WEBHOOK_SECRET")
+                    .expect("Missing --secret argument, or WEBHOOK_SECRET environment variable")
+            };
+
+            webhook::webhook(webhook::WebhookOptions { port, secret }).await?;
+        }
+        None => {
+            let provider = make_provider(&args.provider, resolve_token(args.token))?;
+            let builds = provider.recent_builds().await?;
+
+            let db = DbCtx::open("state.db")?;
+            db.record_builds(&builds, Utc::now())?;
+
+            let repo = Repository::init(".")?;
+
+            let latest = find_builds(builds, &repo);
+
+            if args.notify {
+                notify_on_transition(&db, &repo, &latest, &args.notify_backend)?;
+            }
+
+            if args.analytics {
+                print_analytics(&db, &latest, args.analytics_window)?;
+            } else {
+                print_builds(latest, repo);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Look up the build for the developer's current branch and, if its
+/// outcome just transitioned into failure or recovered, fire a
+/// notification through the configured backend.
+fn notify_on_transition(
+    db: &DbCtx,
+    repo: &Repository,
+    builds: &[Build],
+    backend: &str,
+) -> Result<()> {
+    let current_branch_name = match current_branch_name(repo)? {
+        Some(name) => name,
+        None => return Ok(()),
     };
 
-    let url = format!(
-        "https://circleci.com/api/v1.1/recent-builds?circle-token={token}&limit={limit}",
-        token = token,
-        limit = 100,
-    );
+    let build = match builds.iter().find(|build| build.branch == current_branch_name) {
+        Some(build) => build,
+        None => return Ok(()),
+    };
 
-    let resp: reqwest::Response = reqwest::get(&url).await?;
+    let outcome_kind = match build.outcome.as_ref() {
+        Some(outcome) => outcome.kind(),
+        None => return Ok(()),
+    };
 
-    let builds = resp.json::<Vec<TryBuild>>().await?;
+    // `state.db` already has this build's row recorded (see above), so the
+    // previous outcome is the second-most-recent entry for this branch.
+    let history = db.history(&current_branch_name, 2)?;
+    let previous = history.get(1).and_then(|row| row.outcome.as_deref());
 
-    let builds = builds
-        .into_iter()
-        .filter_map(TryBuild::into_build)
-        .collect::<Vec<_>>();
+    if !notifier::is_notifiable_transition(previous, outcome_kind) {
+        return Ok(());
+    }
+
+    let event = NotifyEvent {
+        branch: &current_branch_name,
+        build_num: build.build_num,
+        outcome_kind,
+        build_url: build.build_url.as_deref(),
+    };
 
-    let repo = Repository::init(".")?;
+    let notifier: Box<dyn Notifier> = match backend {
+        "email" => Box::new(EmailNotifier {
+            smtp_host: std::env::var("SMTP_HOST").map_err(|_| "Missing SMTP_HOST environment variable")?,
+            smtp_username: std::env::var("SMTP_USERNAME")
+                .map_err(|_| "Missing SMTP_USERNAME environment variable")?,
+            smtp_password: std::env::var("SMTP_PASSWORD")
+                .map_err(|_| "Missing SMTP_PASSWORD environment variable")?,
+        }),
+        _ => Box::new(DesktopNotifier),
+    };
+
+    notifier.notify(&event)
+}
+
+/// Compute and print pass rate, flakiness and average duration per branch,
+/// plus a callout of the slowest and flakiest branch.
+fn print_analytics(db: &DbCtx, builds: &[Build], window: u32) -> Result<()> {
+    let mut stats = builds
+        .iter()
+        .map(|build| {
+            let history = db.history(&build.branch, window)?;
+            Ok(analytics::compute(&build.branch, &history))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    stats.sort_unstable_by(|a, b| a.branch.cmp(&b.branch));
+
+    for branch in &stats {
+        println!(
+            "{branch:width$} pass_rate={pass_rate:.0}% flakiness={flakiness:.2} avg_duration={avg_duration}",
+            branch = branch.branch,
+            width = stats
+                .iter()
+                .max_by_key(|b| b.branch.len())
+                .map(|b| b.branch.len())
+                .unwrap_or(0),
+            pass_rate = branch.pass_rate * 100.0,
+            flakiness = branch.flakiness,
+            avg_duration = branch
+                .avg_duration_secs
+                .map(|secs| format!("{:.0}s", secs))
+                .unwrap_or_else(|| "n/a".to_string()),
+        );
+    }
 
-    let builds = find_builds(builds, &repo);
-    print_builds(builds, repo);
+    if let Some(slowest) = stats
+        .iter()
+        .filter(|b| b.avg_duration_secs.is_some())
+        .max_by(|a, b| {
+            a.avg_duration_secs
+                .partial_cmp(&b.avg_duration_secs)
+                .expect("duration is never NaN")
+        })
+    {
+        println!(
+            "slowest branch: {} ({:.0}s avg)",
+            slowest.branch,
+            slowest.avg_duration_secs.unwrap_or_default(),
+        );
+    }
+
+    if let Some(flakiest) = stats
+        .iter()
+        .max_by(|a, b| a.flakiness.partial_cmp(&b.flakiness).expect("flakiness is never NaN"))
+    {
+        println!(
+            "flakiest branch: {} ({:.2} flakiness over {} runs)",
+            flakiest.branch, flakiest.flakiness, flakiest.runs,
+        );
+    }
 
     Ok(())
 }
 
+/// Resolve the CircleCI token from `--token`, falling back to the
+/// `CIRCLECI_TOKEN` environment variable. Only called by the modes that
+/// actually poll CircleCI, so `webhook` never needs one.
+fn resolve_token(token: Option<String>) -> String {
+    token.unwrap_or_else(|| {
+        std::env::var("CIRCLECI_TOKEN")
+            .expect("Missing --token argument, or CIRCLECI_TOKEN environment variable")
+    })
+}
+
 fn current_branch_name(repo: &Repository) -> Result<Option<String>> {
     let head = repo.head()?;
 
@@ -68,7 +271,7 @@ fn current_branch_name(repo: &Repository) -> Result<Option<String>> {
     Ok(None)
 }
 
-fn find_builds(mut builds: Vec<Build>, repo: &Repository) -> Vec<Build> {
+pub(crate) fn find_builds(mut builds: Vec<Build>, repo: &Repository) -> Vec<Build> {
     builds.sort_unstable_by_key(|build| -build.build_num);
 
     let mut builds = builds
@@ -142,35 +345,28 @@ fn pad(s: &str, n: usize) -> String {
     acc
 }
 
-#[derive(Debug, Deserialize)]
-struct TryBuild {
-    branch: Option<String>,
-    build_num: i32,
-    outcome: Option<Outcome>,
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Build {
+    pub(crate) branch: String,
+    pub(crate) build_num: i32,
+    pub(crate) outcome: Option<Outcome>,
+    pub(crate) start_time: Option<DateTime<Utc>>,
+    pub(crate) stop_time: Option<DateTime<Utc>>,
+    pub(crate) build_url: Option<String>,
 }
 
-impl TryBuild {
-    fn into_build(self) -> Option<Build> {
-        let branch = self.branch?;
-        let build_num = self.build_num;
-        let outcome = self.outcome;
-        Some(Build {
-            branch,
-            build_num,
-            outcome,
-        })
+impl Build {
+    /// A comparable snapshot of this build's outcome, used to detect
+    /// transitions between polls without deriving `PartialEq` on `Outcome`
+    /// itself (which would make the `retried`/`canceled` etc. variants
+    /// look like meaningful equality rather than raw CircleCI statuses).
+    fn outcome_kind(&self) -> Option<&'static str> {
+        self.outcome.as_ref().map(Outcome::kind)
     }
 }
 
-#[derive(Debug, Deserialize)]
-struct Build {
-    branch: String,
-    build_num: i32,
-    outcome: Option<Outcome>,
-}
-
-#[derive(Debug, Deserialize)]
-enum Outcome {
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) enum Outcome {
     #[serde(rename = "retried")]
     Retried,
     #[serde(rename = "canceled")]
@@ -226,4 +422,42 @@ impl Outcome {
             _ => false,
         }
     }
+
+    /// Stable identifier for each variant, used where we need to compare
+    /// outcomes (e.g. detecting a transition between polls) without
+    /// pulling in `PartialEq`.
+    fn kind(&self) -> &'static str {
+        use Outcome::*;
+
+        match self {
+            Retried => "retried",
+            Canceled => "canceled",
+            InfrastructureFail => "infrastructure_fail",
+            Timedout => "timedout",
+            NotRun => "not_run",
+            Running => "running",
+            Failed => "failed",
+            Queued => "queued",
+            Scheduled => "scheduled",
+            NotRunning => "not_running",
+            NoTests => "no_tests",
+            Fixed => "fixed",
+            Success => "success",
+        }
+    }
+
+    /// CSS class name for rendering this outcome in the HTML dashboard.
+    pub(crate) fn css_class(&self) -> &'static str {
+        use Outcome::*;
+
+        match self {
+            InfrastructureFail => "infra",
+            Timedout => "timedout",
+            Running => "running",
+            Failed => "failed",
+            Scheduled => "scheduled",
+            Fixed | Success => "ok",
+            Retried | Canceled | NotRun | Queued | NotRunning | NoTests => "",
+        }
+    }
 }
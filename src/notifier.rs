@@ -0,0 +1,134 @@
+use git2::Repository;
+use lettre::message::Message;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{SmtpTransport, Transport};
+
+use crate::Result;
+
+/// A build outcome transition worth telling someone about.
+pub(crate) struct NotifyEvent<'a> {
+    pub(crate) branch: &'a str,
+    pub(crate) build_num: i32,
+    pub(crate) outcome_kind: &'a str,
+    pub(crate) build_url: Option<&'a str>,
+}
+
+/// Terminal failing outcomes, per `Outcome::kind`.
+const FAILING_KINDS: &[&str] = &["failed", "timedout", "infrastructure_fail"];
+
+/// Outcomes that mean the branch recovered, per `Outcome::kind`.
+const RECOVERED_KINDS: &[&str] = &["fixed", "success"];
+
+/// Whether moving from `previous` to `current` is a transition the notifier
+/// subsystem should fire on: newly failing, or newly recovered.
+pub(crate) fn is_notifiable_transition(previous: Option<&str>, current: &str) -> bool {
+    let was_failing = previous.map(|kind| FAILING_KINDS.contains(&kind)).unwrap_or(false);
+    let now_failing = FAILING_KINDS.contains(&current);
+    let now_recovered = RECOVERED_KINDS.contains(&current);
+
+    (now_failing && !was_failing) || (now_recovered && was_failing)
+}
+
+/// A backend capable of delivering a `NotifyEvent` to a developer.
+pub(crate) trait Notifier {
+    fn notify(&self, event: &NotifyEvent) -> Result<()>;
+}
+
+fn message_body(event: &NotifyEvent) -> String {
+    format!(
+        "{branch} build #{build_num} is now {outcome}{url}",
+        branch = event.branch,
+        build_num = event.build_num,
+        outcome = event.outcome_kind,
+        url = event
+            .build_url
+            .map(|url| format!(" — {}", url))
+            .unwrap_or_default(),
+    )
+}
+
+/// Fires a desktop notification via the OS notification center.
+pub(crate) struct DesktopNotifier;
+
+impl Notifier for DesktopNotifier {
+    fn notify(&self, event: &NotifyEvent) -> Result<()> {
+        notify_rust::Notification::new()
+            .summary(&format!("ci-dashboard: {}", event.branch))
+            .body(&message_body(event))
+            .show()?;
+
+        Ok(())
+    }
+}
+
+/// Emails the author of the current HEAD commit, the way pushmail does.
+pub(crate) struct EmailNotifier {
+    pub(crate) smtp_host: String,
+    pub(crate) smtp_username: String,
+    pub(crate) smtp_password: String,
+}
+
+impl Notifier for EmailNotifier {
+    fn notify(&self, event: &NotifyEvent) -> Result<()> {
+        let repo = Repository::init(".")?;
+        let head = repo.head()?.peel_to_commit()?;
+        let author = head.author();
+
+        let to = author
+            .email()
+            .ok_or("HEAD commit author has no email set")?;
+
+        let email = Message::builder()
+            .from(self.smtp_username.parse()?)
+            .to(to.parse()?)
+            .subject(format!("ci-dashboard: {} is now {}", event.branch, event.outcome_kind))
+            .body(message_body(event))?;
+
+        let creds = Credentials::new(self.smtp_username.clone(), self.smtp_password.clone());
+
+        let transport = SmtpTransport::relay(&self.smtp_host)?
+            .credentials(creds)
+            .build();
+
+        transport.send(&email)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_ever_failure_notifies() {
+        assert!(is_notifiable_transition(None, "failed"));
+    }
+
+    #[test]
+    fn first_ever_success_does_not_notify() {
+        assert!(!is_notifiable_transition(None, "success"));
+    }
+
+    #[test]
+    fn repeated_failure_does_not_renotify() {
+        assert!(!is_notifiable_transition(Some("failed"), "timedout"));
+    }
+
+    #[test]
+    fn recovery_from_failure_notifies() {
+        assert!(is_notifiable_transition(Some("failed"), "success"));
+        assert!(is_notifiable_transition(Some("infrastructure_fail"), "fixed"));
+    }
+
+    #[test]
+    fn repeated_success_does_not_renotify() {
+        assert!(!is_notifiable_transition(Some("success"), "fixed"));
+    }
+
+    #[test]
+    fn non_terminal_outcomes_do_not_notify() {
+        assert!(!is_notifiable_transition(Some("success"), "running"));
+        assert!(!is_notifiable_transition(Some("running"), "queued"));
+    }
+}
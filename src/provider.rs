@@ -0,0 +1,75 @@
+use async_trait::async_trait;
+use reqwest;
+use serde_derive::Deserialize;
+
+use crate::{Build, Outcome, Result};
+use chrono::{DateTime, Utc};
+
+/// A source of recent CI builds, abstracted so the rest of the pipeline
+/// (`find_builds`, `print_builds`, the SSE dashboard, analytics, ...)
+/// never needs to know which CI system is actually being polled.
+#[async_trait]
+pub(crate) trait CiProvider {
+    async fn recent_builds(&self) -> Result<Vec<Build>>;
+}
+
+/// Build a provider for `kind`, the value of `--provider` (or the
+/// `CI_PROVIDER` environment variable it defaults from).
+pub(crate) fn make_provider(kind: &str, token: String) -> Result<Box<dyn CiProvider + Send + Sync>> {
+    match kind {
+        "circleci" => Ok(Box::new(CircleCi { token })),
+        other => Err(format!("unknown CI provider {:?} (expected \"circleci\")", other).into()),
+    }
+}
+
+/// CircleCI v1.1 `recent-builds`, mapping its native `outcome` vocabulary
+/// onto the shared `Outcome` enum via `TryBuild`'s `Deserialize` impl.
+pub(crate) struct CircleCi {
+    token: String,
+}
+
+#[async_trait]
+impl CiProvider for CircleCi {
+    async fn recent_builds(&self) -> Result<Vec<Build>> {
+        let url = format!(
+            "https://circleci.com/api/v1.1/recent-builds?circle-token={token}&limit={limit}",
+            token = self.token,
+            limit = 100,
+        );
+
+        let resp: reqwest::Response = reqwest::get(&url).await?;
+
+        let builds = resp.json::<Vec<TryBuild>>().await?;
+
+        Ok(builds
+            .into_iter()
+            .filter_map(TryBuild::into_build)
+            .collect::<Vec<_>>())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TryBuild {
+    branch: Option<String>,
+    build_num: i32,
+    outcome: Option<Outcome>,
+    start_time: Option<DateTime<Utc>>,
+    stop_time: Option<DateTime<Utc>>,
+    build_url: Option<String>,
+}
+
+impl TryBuild {
+    fn into_build(self) -> Option<Build> {
+        let branch = self.branch?;
+        let build_num = self.build_num;
+        let outcome = self.outcome;
+        Some(Build {
+            branch,
+            build_num,
+            outcome,
+            start_time: self.start_time,
+            stop_time: self.stop_time,
+            build_url: self.build_url,
+        })
+    }
+}
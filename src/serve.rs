@@ -0,0 +1,235 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::Extension;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::Html;
+use axum::routing::get;
+use axum::Router;
+use futures::stream::Stream;
+use git2::Repository;
+use tokio::sync::{mpsc, RwLock};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+
+use crate::provider::CiProvider;
+use crate::{find_builds, Build, Outcome, Result};
+
+/// Options for the `serve` subcommand.
+pub struct ServeOptions {
+    pub port: u16,
+    pub interval_secs: u64,
+}
+
+/// Shared state handed to every axum handler.
+///
+/// `builds` holds the latest per-branch snapshot used to render the
+/// initial page; `clients` holds one sender per connected browser so a
+/// single update can fan out to everyone watching. Shared with the
+/// `webhook` mode, which merges events into the same struct instead of
+/// polling for them.
+pub(crate) struct Dashboard {
+    pub(crate) builds: RwLock<Vec<Build>>,
+    clients: RwLock<Vec<mpsc::Sender<String>>>,
+}
+
+impl Dashboard {
+    pub(crate) fn new(builds: Vec<Build>) -> Self {
+        Dashboard {
+            builds: RwLock::new(builds),
+            clients: RwLock::new(Vec::new()),
+        }
+    }
+
+    pub(crate) async fn broadcast(&self, message: String) {
+        let mut clients = self.clients.write().await;
+        let mut alive = Vec::with_capacity(clients.len());
+        for client in clients.drain(..) {
+            if client.send(message.clone()).await.is_ok() {
+                alive.push(client);
+            }
+        }
+        *clients = alive;
+    }
+}
+
+/// The `/` and `/events` routes shared by `serve` and `webhook` mode.
+pub(crate) fn dashboard_routes() -> Router {
+    Router::new().route("/", get(index)).route("/events", get(events))
+}
+
+pub(crate) fn render_build_rows(builds: &[Build]) -> String {
+    render_rows(builds)
+}
+
+/// Start the long-running web dashboard: serve the build table as HTML
+/// and push incremental updates to connected browsers over SSE.
+pub async fn serve(provider: Box<dyn CiProvider + Send + Sync>, opts: ServeOptions) -> Result<()> {
+    let repo = Repository::init(".")?;
+
+    let builds = provider.recent_builds().await?;
+    let builds = find_builds(builds, &repo);
+
+    let dashboard = Arc::new(Dashboard::new(builds));
+
+    let poller = dashboard.clone();
+    tokio::spawn(async move {
+        poll_loop(provider, opts.interval_secs, poller).await;
+    });
+
+    let app = dashboard_routes().layer(Extension(dashboard));
+
+    let addr = ([0, 0, 0, 0], opts.port).into();
+    axum::Server::bind(&addr)
+        .serve(app.into_make_service())
+        .await?;
+
+    Ok(())
+}
+
+async fn poll_loop(
+    provider: Box<dyn CiProvider + Send + Sync>,
+    interval_secs: u64,
+    dashboard: Arc<Dashboard>,
+) {
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+
+    loop {
+        interval.tick().await;
+
+        let repo = match Repository::init(".") {
+            Ok(repo) => repo,
+            Err(_) => continue,
+        };
+
+        let builds = match provider.recent_builds().await {
+            Ok(builds) => builds,
+            Err(_) => continue,
+        };
+        let builds = find_builds(builds, &repo);
+
+        let changed = {
+            let previous = dashboard.builds.read().await;
+            builds.iter().any(|build| {
+                previous
+                    .iter()
+                    .find(|prev| prev.branch == build.branch)
+                    .map(|prev| prev.outcome_kind() != build.outcome_kind())
+                    .unwrap_or(true)
+            })
+        };
+
+        if changed {
+            *dashboard.builds.write().await = builds.clone();
+            dashboard.broadcast(render_rows(&builds)).await;
+        }
+    }
+}
+
+async fn index(Extension(dashboard): Extension<Arc<Dashboard>>) -> Html<String> {
+    let builds = dashboard.builds.read().await;
+    Html(render_page(&builds))
+}
+
+async fn events(
+    Extension(dashboard): Extension<Arc<Dashboard>>,
+) -> Sse<impl Stream<Item = std::result::Result<Event, Infallible>>> {
+    let (tx, rx) = mpsc::channel(16);
+    dashboard.clients.write().await.push(tx);
+
+    let stream = ReceiverStream::new(rx).map(|rows| Ok(Event::default().data(rows)));
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+fn render_page(builds: &[Build]) -> String {
+    format!(
+        r#"<!doctype html>
+<html>
+<head>
+<title>ci-dashboard</title>
+<style>
+body {{ font-family: monospace; background: #111; color: #eee; }}
+table {{ border-collapse: collapse; }}
+td {{ padding: 2px 12px; }}
+.ok {{ color: #4caf50; }}
+.failed, .timedout, .infra {{ color: #f44336; }}
+.running {{ color: #2196f3; }}
+.scheduled {{ color: #e040fb; }}
+</style>
+</head>
+<body>
+<table id="builds">
+{rows}
+</table>
+<script>
+const source = new EventSource("/events");
+source.onmessage = (event) => {{
+    document.getElementById("builds").innerHTML = event.data;
+}};
+</script>
+</body>
+</html>"#,
+        rows = render_rows(builds),
+    )
+}
+
+fn render_rows(builds: &[Build]) -> String {
+    builds
+        .iter()
+        .map(|build| {
+            format!(
+                "<tr><td>{branch}</td><td class=\"{class}\">{outcome}</td><td>{build_num}</td></tr>",
+                branch = escape(&build.branch),
+                class = build
+                    .outcome
+                    .as_ref()
+                    .map(Outcome::css_class)
+                    .unwrap_or("pending"),
+                outcome = escape(
+                    &build
+                        .outcome
+                        .as_ref()
+                        .map(Outcome::term_string)
+                        .unwrap_or_else(|| "no outcome (yet)".to_string())
+                ),
+                build_num = build.build_num,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Escape the characters that matter when splicing untrusted text (a
+/// branch name, a CI-provided build URL, ...) into HTML served by the
+/// dashboard. `&` must be escaped first so it doesn't double-escape the
+/// entities this introduces.
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_rows_escapes_branch_names() {
+        let build = Build {
+            branch: "feature/<script>alert(1)</script>&friends".to_string(),
+            build_num: 42,
+            outcome: None,
+            start_time: None,
+            stop_time: None,
+            build_url: None,
+        };
+
+        let html = render_rows(&[build]);
+
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(html.contains("&amp;friends"));
+    }
+}
@@ -0,0 +1,238 @@
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use axum::extract::Extension;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::post;
+use axum::Router;
+use git2::{BranchType, Repository};
+use hmac::{Hmac, Mac};
+use serde_derive::Deserialize;
+use sha2::Sha256;
+
+use crate::serve::{dashboard_routes, render_build_rows, Dashboard};
+use crate::{Build, Outcome, Result};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Options for the `webhook` subcommand.
+pub struct WebhookOptions {
+    pub port: u16,
+    pub secret: String,
+}
+
+/// Start a webhook receiver: build status updates arrive as signed HTTP
+/// POSTs instead of being polled for, and are merged into the same
+/// `Dashboard` the `serve` subcommand renders.
+pub async fn webhook(opts: WebhookOptions) -> Result<()> {
+    let dashboard = Arc::new(Dashboard::new(Vec::new()));
+
+    let app = dashboard_routes()
+        .route("/webhook", post(receive))
+        .layer(Extension(dashboard))
+        .layer(Extension(Arc::new(opts.secret)));
+
+    let addr = ([0, 0, 0, 0], opts.port).into();
+    axum::Server::bind(&addr)
+        .serve(app.into_make_service())
+        .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct WebhookPayload {
+    branch: String,
+    build_num: i32,
+    status: String,
+    build_url: Option<String>,
+}
+
+async fn receive(
+    Extension(dashboard): Extension<Arc<Dashboard>>,
+    Extension(secret): Extension<Arc<String>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    let signature = match headers
+        .get("X-Hub-Signature-256")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("sha256="))
+    {
+        Some(signature) => signature,
+        None => return StatusCode::UNAUTHORIZED,
+    };
+
+    if !verify_signature(secret.as_bytes(), &body, signature) {
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    let payload = match serde_json::from_slice::<WebhookPayload>(&body) {
+        Ok(payload) => payload,
+        Err(_) => return StatusCode::BAD_REQUEST,
+    };
+
+    let outcome = match status_to_outcome(&payload.status) {
+        Some(outcome) => outcome,
+        None => return StatusCode::OK,
+    };
+
+    let known_branch = Repository::init(".")
+        .and_then(|repo| repo.find_branch(&payload.branch, BranchType::Local).map(|_| ()))
+        .is_ok();
+
+    if !known_branch {
+        return StatusCode::OK;
+    }
+
+    let build = Build {
+        branch: payload.branch,
+        build_num: payload.build_num,
+        outcome: Some(outcome),
+        start_time: None,
+        stop_time: None,
+        build_url: payload.build_url,
+    };
+
+    merge_build(&dashboard, build).await;
+
+    StatusCode::OK
+}
+
+/// Hex-decode `signature` and compare it in constant time against the
+/// HMAC-SHA256 of the raw request body computed with `secret`.
+fn verify_signature(secret: &[u8], body: &[u8], signature: &str) -> bool {
+    let expected = match hex::decode(signature) {
+        Ok(bytes) => bytes,
+        Err(_) => return false,
+    };
+
+    let mut mac = match HmacSha256::new_from_slice(secret) {
+        Ok(mac) => mac,
+        Err(_) => return false,
+    };
+    mac.update(body);
+
+    mac.verify_slice(&expected).is_ok()
+}
+
+fn status_to_outcome(status: &str) -> Option<Outcome> {
+    match status {
+        "success" | "fixed" => Some(Outcome::Success),
+        "failed" | "failure" => Some(Outcome::Failed),
+        "timedout" | "timed_out" => Some(Outcome::Timedout),
+        "infrastructure_fail" => Some(Outcome::InfrastructureFail),
+        "canceled" | "cancelled" => Some(Outcome::Canceled),
+        "running" | "in_progress" => Some(Outcome::Running),
+        "queued" => Some(Outcome::Queued),
+        "scheduled" => Some(Outcome::Scheduled),
+        "not_run" => Some(Outcome::NotRun),
+        "not_running" => Some(Outcome::NotRunning),
+        "no_tests" => Some(Outcome::NoTests),
+        "retried" => Some(Outcome::Retried),
+        _ => None,
+    }
+}
+
+/// Replace `build`'s branch in the dashboard's build table and broadcast
+/// the new table to connected clients if anything changed.
+async fn merge_build(dashboard: &Dashboard, build: Build) {
+    let mut builds = dashboard.builds.write().await;
+
+    let changed = match builds.iter_mut().find(|existing| existing.branch == build.branch) {
+        Some(existing) => {
+            let changed = existing.build_num != build.build_num
+                || existing.outcome_kind() != build.outcome_kind();
+            *existing = build;
+            changed
+        }
+        None => {
+            builds.push(build);
+            true
+        }
+    };
+
+    if changed {
+        dashboard.broadcast(render_build_rows(&builds)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_to_outcome_maps_known_statuses() {
+        assert!(matches!(status_to_outcome("success"), Some(Outcome::Success)));
+        assert!(matches!(status_to_outcome("fixed"), Some(Outcome::Success)));
+        assert!(matches!(status_to_outcome("failed"), Some(Outcome::Failed)));
+        assert!(matches!(status_to_outcome("failure"), Some(Outcome::Failed)));
+        assert!(matches!(status_to_outcome("timed_out"), Some(Outcome::Timedout)));
+        assert!(matches!(status_to_outcome("cancelled"), Some(Outcome::Canceled)));
+    }
+
+    #[test]
+    fn status_to_outcome_rejects_unknown_status() {
+        assert!(status_to_outcome("bogus").is_none());
+    }
+
+    #[test]
+    fn verify_signature_accepts_matching_hmac() {
+        let secret = b"shared-secret";
+        let body = b"{\"branch\":\"main\"}";
+
+        let mut mac = HmacSha256::new_from_slice(secret).unwrap();
+        mac.update(body);
+        let signature = hex::encode(mac.finalize().into_bytes());
+
+        assert!(verify_signature(secret, body, &signature));
+    }
+
+    #[test]
+    fn verify_signature_rejects_wrong_secret() {
+        let body = b"{\"branch\":\"main\"}";
+
+        let mut mac = HmacSha256::new_from_slice(b"shared-secret").unwrap();
+        mac.update(body);
+        let signature = hex::encode(mac.finalize().into_bytes());
+
+        assert!(!verify_signature(b"wrong-secret", body, &signature));
+    }
+
+    #[test]
+    fn verify_signature_rejects_tampered_body() {
+        let secret = b"shared-secret";
+
+        let mut mac = HmacSha256::new_from_slice(secret).unwrap();
+        mac.update(b"{\"branch\":\"main\"}");
+        let signature = hex::encode(mac.finalize().into_bytes());
+
+        assert!(!verify_signature(secret, b"{\"branch\":\"evil\"}", &signature));
+    }
+
+    #[test]
+    fn verify_signature_rejects_malformed_hex() {
+        assert!(!verify_signature(b"shared-secret", b"body", "not-hex"));
+    }
+
+    #[test]
+    fn merged_builds_render_through_the_escaping_renderer() {
+        // A webhook payload's branch is attacker-controlled (the HMAC check
+        // only proves CircleCI/GitHub sent it, not that it's safe HTML) --
+        // it must go through the same `render_build_rows` escaping as the
+        // polling paths.
+        let build = Build {
+            branch: "<script>alert(1)</script>".to_string(),
+            build_num: 1,
+            outcome: Some(Outcome::Success),
+            start_time: None,
+            stop_time: None,
+            build_url: None,
+        };
+
+        let html = render_build_rows(std::slice::from_ref(&build));
+
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;script&gt;"));
+    }
+}